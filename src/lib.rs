@@ -33,9 +33,22 @@
 //! }
 //! ```
 use std::cmp::max;
+use std::convert::TryInto;
 use std::fmt::Display;
 use std::marker::PhantomData;
 
+/// Magic bytes identifying the on-disk format written by `ConsolidatedMap::to_bytes`.
+const MAGIC: &[u8; 4] = b"CMAP";
+
+/// Version of the on-disk format written by `ConsolidatedMap::to_bytes`.
+const FORMAT_VERSION: u32 = 2;
+
+/// Size in bytes of the fixed header: magic (4) + version (4) + data len (8) + index len (8).
+const HEADER_LEN: usize = 24;
+
+/// Sentinel stored in `ConsolidatedMap::parents` for an item with no parent.
+const NO_PARENT: u32 = u32::MAX;
+
 /// A consolidated map that represent a list of children associated with a key.
 ///
 /// The ConsolidatedMap is readonly and must be build using the ConsolidatedMapBuilder
@@ -44,6 +57,12 @@ pub struct ConsolidatedMap<T> {
     _t: PhantomData<T>,
     data: Vec<u32>,
     index: Vec<usize>,
+    /// `parents[item]` is the parent of `item`, or `NO_PARENT` for a root.
+    parents: Vec<u32>,
+    /// CSR-style immediate children of each item, derived from `parents`:
+    /// `child_ids[child_offsets[item]..child_offsets[item + 1]]`.
+    child_offsets: Vec<usize>,
+    child_ids: Vec<u32>,
 }
 
 impl<T> Clone for ConsolidatedMap<T> {
@@ -52,6 +71,9 @@ impl<T> Clone for ConsolidatedMap<T> {
             _t: PhantomData,
             data: self.data.clone(),
             index: self.index.clone(),
+            parents: self.parents.clone(),
+            child_offsets: self.child_offsets.clone(),
+            child_ids: self.child_ids.clone(),
         }
     }
 }
@@ -108,15 +130,66 @@ impl<T> ConsolidatedMap<T> {
     }
 
     /// Returns true if a parent is contains the child.
+    ///
+    /// The children slice is kept sorted by `ConsolidatedMapBuilder::build`,
+    /// so this uses a binary search instead of a linear scan.
     pub fn contains_child(&self, parent: T, child: T) -> bool
     where
         T: Into<usize>,
     {
         self.get_children_slice(parent)
-            .map(|data| data.contains(&(child.into() as u32)))
+            .map(|data| data.binary_search(&(child.into() as u32)).is_ok())
             .unwrap_or(false)
     }
 
+    /// Returns true if `node` is a descendant of `ancestor`.
+    ///
+    /// This is a binary search over `ancestor`'s consolidated children slice.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(1, 2), (2, 3)].into_iter().collect();
+    ///
+    /// assert!(map.is_descendant(1, 3));
+    /// assert!(!map.is_descendant(3, 1));
+    /// ```
+    pub fn is_descendant(&self, ancestor: T, node: T) -> bool
+    where
+        T: Into<usize>,
+    {
+        self.contains_child(ancestor, node)
+    }
+
+    /// Returns an iterator of the keys that are descendants of both `a` and `b`.
+    ///
+    /// Both consolidated slices are already sorted, so they are merged
+    /// together in a single linear pass to yield their intersection, without
+    /// collecting either side into a `HashSet`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// // 1 is the parent of 2, which is itself the parent of 3 and 4.
+    /// let map: ConsolidatedMap<usize> = vec![(1, 2), (2, 3), (2, 4)].into_iter().collect();
+    ///
+    /// assert_eq!(map.common_descendants(1, 2).collect::<Vec<_>>(), vec![3, 4]);
+    /// ```
+    pub fn common_descendants(&self, a: T, b: T) -> CommonDescendants<T>
+    where
+        T: Into<usize>,
+    {
+        CommonDescendants(
+            self.get_children_slice(a).unwrap_or(&[]).iter().peekable(),
+            self.get_children_slice(b).unwrap_or(&[]).iter().peekable(),
+            PhantomData,
+        )
+    }
+
     fn get_children_slice(&self, parent: T) -> Option<&[u32]>
     where
         T: Into<usize>,
@@ -126,14 +199,499 @@ impl<T> ConsolidatedMap<T> {
         let len = *self.data.get(index)? as usize;
         Some(&self.data[index + 1..index + 1 + len])
     }
+
+    /// Returns the parent of `item`, or `None` if it is a root or was never inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(1, 2), (2, 3)].into_iter().collect();
+    ///
+    /// assert_eq!(map.parent(3), Some(2));
+    /// assert_eq!(map.parent(1), None);
+    /// ```
+    pub fn parent(&self, item: T) -> Option<T>
+    where
+        T: From<usize> + Into<usize>,
+    {
+        let parent = *self.parents.get(item.into())?;
+
+        if parent == NO_PARENT {
+            None
+        } else {
+            Some((parent as usize).into())
+        }
+    }
+
+    /// Returns an iterator that walks from the parent of `item` up to the root.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(1, 2), (2, 3)].into_iter().collect();
+    ///
+    /// assert_eq!(map.ancestors(3).collect::<Vec<_>>(), vec![2, 1]);
+    /// assert_eq!(map.ancestors(1).collect::<Vec<_>>(), Vec::<usize>::new());
+    /// ```
+    pub fn ancestors(&self, item: T) -> Ancestors<T>
+    where
+        T: Copy + From<usize> + Into<usize>,
+    {
+        Ancestors {
+            map: self,
+            current: Some(item),
+        }
+    }
+
+    /// Returns an iterator over the items that have no parent.
+    ///
+    /// See `ConsolidatedMapBuilder`'s docs for the sparse-key-space phantom
+    /// entries this also picks up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// // key 0 must be used by an edge, otherwise the dense key space
+    /// // between 0 and the highest key still allocates it as an unused root.
+    /// let map: ConsolidatedMap<usize> = vec![(0, 1), (1, 2)].into_iter().collect();
+    ///
+    /// assert_eq!(map.roots().collect::<Vec<_>>(), vec![0]);
+    /// ```
+    pub fn roots(&self) -> Roots<T>
+    where
+        T: From<usize>,
+    {
+        Roots {
+            parents: self.parents.iter().enumerate(),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the items that have no children.
+    ///
+    /// See `ConsolidatedMapBuilder`'s docs for the sparse-key-space phantom
+    /// entries this also picks up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(0, 1), (1, 2)].into_iter().collect();
+    ///
+    /// assert_eq!(map.leaves().collect::<Vec<_>>(), vec![2]);
+    /// ```
+    pub fn leaves(&self) -> Leaves<T>
+    where
+        T: From<usize>,
+    {
+        Leaves {
+            index: self.index.iter().enumerate(),
+            data: &self.data,
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a depth-first, pre-order iterator over every item of the
+    /// forest, starting from each root in turn.
+    ///
+    /// See `ConsolidatedMapBuilder`'s docs for the sparse-key-space phantom
+    /// entries this also visits, each as its own single-item root.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> =
+    ///     vec![(0, 1), (1, 2), (1, 3), (4, 5)].into_iter().collect();
+    ///
+    /// assert_eq!(map.preorder().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+    /// ```
+    pub fn preorder(&self) -> Preorder<T>
+    where
+        T: From<usize> + Into<usize>,
+    {
+        let mut stack: Vec<u32> = self
+            .parents
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p == NO_PARENT)
+            .map(|(i, _)| i as u32)
+            .collect();
+
+        stack.reverse();
+
+        Preorder {
+            map: self,
+            stack,
+            _t: PhantomData,
+        }
+    }
+
+    /// Folds `f` over the value of `item` and all of its descendants.
+    ///
+    /// `values` is a slice of values indexed like the map's keys; an item
+    /// without a corresponding value is skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(0, 1), (1, 2)].into_iter().collect();
+    /// let values = vec![10u32, 20, 30];
+    ///
+    /// assert_eq!(map.aggregate(0, &values, 0, |acc, v| acc + v), 60);
+    /// assert_eq!(map.aggregate(1, &values, 0, |acc, v| acc + v), 50);
+    /// ```
+    pub fn aggregate<V, B>(&self, item: T, values: &[V], init: B, f: impl Fn(B, &V) -> B) -> B
+    where
+        T: Copy + From<usize> + Into<usize>,
+    {
+        self.consolidated(item)
+            .fold(init, |acc, key| match values.get(key.into()) {
+                Some(v) => f(acc, v),
+                None => acc,
+            })
+    }
+
+    /// Computes the subtree aggregate of every item in one pass, by combining
+    /// each child's already-resolved subtree aggregate into its parent
+    /// (children are always resolved before their parent).
+    ///
+    /// `values` is a slice of per-key values indexed like the map's keys; an
+    /// item without a corresponding value starts from `V::empty()`. The
+    /// result is a `Vec<V>` indexed the same way, where `result[item]` is the
+    /// combination of `values[item]` with the aggregates of all of `item`'s
+    /// descendants.
+    ///
+    /// See `ConsolidatedMapBuilder`'s docs for the sparse-key-space phantom
+    /// entries the result vector, and the work done to produce it, also
+    /// covers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::{ConsolidatedMap, Monoid};
+    ///
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Sum(u32);
+    ///
+    /// impl Monoid for Sum {
+    ///     fn empty() -> Self {
+    ///         Sum(0)
+    ///     }
+    ///
+    ///     fn combine(&self, other: &Self) -> Self {
+    ///         Sum(self.0 + other.0)
+    ///     }
+    /// }
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(0, 1), (1, 2)].into_iter().collect();
+    /// let values = vec![Sum(10), Sum(20), Sum(30)];
+    ///
+    /// assert_eq!(map.rollup(&values), vec![Sum(60), Sum(50), Sum(30)]);
+    /// ```
+    pub fn rollup<V>(&self, values: &[V]) -> Vec<V>
+    where
+        T: From<usize> + Into<usize>,
+        V: Monoid + Clone,
+    {
+        let n = self.parents.len();
+        let mut result: Vec<Option<V>> = (0..n).map(|_| None).collect();
+
+        let mut stack: Vec<(u32, bool)> = self
+            .parents
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p == NO_PARENT)
+            .map(|(i, _)| (i as u32, false))
+            .collect();
+
+        stack.reverse();
+
+        while let Some((node, ready)) = stack.pop() {
+            let start = self.child_offsets[node as usize];
+            let end = self.child_offsets[node as usize + 1];
+
+            if ready {
+                let mut combined = values
+                    .get(node as usize)
+                    .cloned()
+                    .unwrap_or_else(V::empty);
+
+                for &child in &self.child_ids[start..end] {
+                    let child_aggregate = result[child as usize]
+                        .as_ref()
+                        .expect("child aggregate must be resolved before its parent");
+                    combined = combined.combine(child_aggregate);
+                }
+
+                result[node as usize] = Some(combined);
+            } else {
+                stack.push((node, true));
+                stack.extend(self.child_ids[start..end].iter().map(|&c| (c, false)));
+            }
+        }
+
+        result
+            .into_iter()
+            .map(|v| v.unwrap_or_else(V::empty))
+            .collect()
+    }
+
+    /// Serializes the map to a stable, little-endian on-disk format: a fixed
+    /// header (magic, format version, `data.len()`, `index.len()`) followed
+    /// by `data` as `u32`s, `index` as `u64`s and `parents` as `u32`s.
+    ///
+    /// The bytes can be reloaded with `ConsolidatedMap::from_bytes`, or
+    /// opened without copying with `ConsolidatedMapView::from_bytes` (e.g.
+    /// from an `mmap`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::ConsolidatedMap;
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(1, 2), (2, 3)].into_iter().collect();
+    /// let bytes = map.to_bytes();
+    /// let reloaded = ConsolidatedMap::<usize>::from_bytes(&bytes).unwrap();
+    ///
+    /// assert_eq!(reloaded.consolidated(1).collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            HEADER_LEN + self.data.len() * 4 + self.index.len() * 8 + self.parents.len() * 4,
+        );
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.index.len() as u64).to_le_bytes());
+
+        for v in &self.data {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+
+        for v in &self.index {
+            bytes.extend_from_slice(&(*v as u64).to_le_bytes());
+        }
+
+        for v in &self.parents {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Reloads a map previously written by `to_bytes`.
+    ///
+    /// The header, every `index[p] + 1 + data[index[p]]` bound, the
+    /// sortedness of each resulting children slice, and the acyclicity of
+    /// `parents` are all validated before the bytes are trusted.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ConsolidatedMapError> {
+        let header = Header::parse(bytes)?;
+
+        let mut data = Vec::with_capacity(header.data_len);
+        for i in 0..header.data_len {
+            let o = header.data_offset + i * 4;
+            data.push(u32::from_le_bytes(bytes[o..o + 4].try_into().unwrap()));
+        }
+
+        let mut index = Vec::with_capacity(header.index_len);
+        for i in 0..header.index_len {
+            let o = header.index_offset + i * 8;
+            index.push(u64::from_le_bytes(bytes[o..o + 8].try_into().unwrap()) as usize);
+        }
+
+        let mut parents = Vec::with_capacity(header.index_len);
+        for i in 0..header.index_len {
+            let o = header.parents_offset + i * 4;
+            parents.push(u32::from_le_bytes(bytes[o..o + 4].try_into().unwrap()));
+        }
+
+        validate_bounds(&data, &index)?;
+        validate_parents(&parents)?;
+
+        let child_offsets = child_offsets_from_parents(&parents);
+        let child_ids = child_ids_from_parents(&parents, &child_offsets);
+
+        Ok(ConsolidatedMap {
+            _t: PhantomData,
+            data,
+            index,
+            parents,
+            child_offsets,
+            child_ids,
+        })
+    }
+}
+
+/// Checks that every `index[p]` points at a `data[p]` length prefix whose
+/// slice `data[p + 1..p + 1 + len]` stays within bounds and is sorted, since
+/// `contains_child`/`is_descendant`/`common_descendants` binary-search and
+/// merge these slices assuming that invariant.
+fn validate_bounds(data: &[u32], index: &[usize]) -> Result<(), ConsolidatedMapError> {
+    for &p in index {
+        let len = *data.get(p).ok_or(ConsolidatedMapError::OutOfBounds)? as usize;
+
+        let end = p
+            .checked_add(1 + len)
+            .filter(|&end| end <= data.len())
+            .ok_or(ConsolidatedMapError::OutOfBounds)?;
+
+        if !data[p + 1..end].is_sorted() {
+            return Err(ConsolidatedMapError::Unsorted);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every non-`NO_PARENT` entry of `parents` points at another
+/// valid item, and that following `parent` links from every item reaches
+/// `NO_PARENT` within `parents.len()` steps (i.e. the forest has no cycles).
+fn validate_parents(parents: &[u32]) -> Result<(), ConsolidatedMapError> {
+    for &p in parents {
+        if p != NO_PARENT && p as usize >= parents.len() {
+            return Err(ConsolidatedMapError::OutOfBounds);
+        }
+    }
+
+    for start in 0..parents.len() {
+        let mut current = start as u32;
+
+        for _ in 0..parents.len() {
+            current = parents[current as usize];
+
+            if current == NO_PARENT {
+                break;
+            }
+        }
+
+        if current != NO_PARENT {
+            return Err(ConsolidatedMapError::Cyclic);
+        }
+    }
+
+    Ok(())
+}
+
+/// The parsed and bounds-checked fixed header shared by `ConsolidatedMap::from_bytes`
+/// and `ConsolidatedMapView::from_bytes`.
+struct Header {
+    data_len: usize,
+    index_len: usize,
+    data_offset: usize,
+    index_offset: usize,
+    parents_offset: usize,
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Result<Self, ConsolidatedMapError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(ConsolidatedMapError::Truncated);
+        }
+
+        if &bytes[0..4] != MAGIC {
+            return Err(ConsolidatedMapError::InvalidMagic);
+        }
+
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(ConsolidatedMapError::UnsupportedVersion(version));
+        }
+
+        let data_len = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let index_len = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+
+        let data_offset = HEADER_LEN;
+        let data_bytes = data_len.checked_mul(4).ok_or(ConsolidatedMapError::Truncated)?;
+        let index_offset = data_offset
+            .checked_add(data_bytes)
+            .ok_or(ConsolidatedMapError::Truncated)?;
+        let index_bytes = index_len.checked_mul(8).ok_or(ConsolidatedMapError::Truncated)?;
+        let parents_offset = index_offset
+            .checked_add(index_bytes)
+            .ok_or(ConsolidatedMapError::Truncated)?;
+        let parents_bytes = index_len.checked_mul(4).ok_or(ConsolidatedMapError::Truncated)?;
+        let end = parents_offset
+            .checked_add(parents_bytes)
+            .ok_or(ConsolidatedMapError::Truncated)?;
+
+        if bytes.len() < end {
+            return Err(ConsolidatedMapError::Truncated);
+        }
+
+        Ok(Header {
+            data_len,
+            index_len,
+            data_offset,
+            index_offset,
+            parents_offset,
+        })
+    }
+}
+
+/// An error returned when reading a `ConsolidatedMap` or `ConsolidatedMapView`
+/// from bytes that are not a valid, trustworthy on-disk map.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConsolidatedMapError {
+    /// The byte slice is too short to contain the header or the arrays it describes.
+    Truncated,
+    /// The header does not start with the expected magic bytes.
+    InvalidMagic,
+    /// The header declares a format version this crate does not know how to read.
+    UnsupportedVersion(u32),
+    /// An `index` entry points outside of the `data` array, or a `parents`
+    /// entry names an item that does not exist.
+    OutOfBounds,
+    /// A children slice is not sorted, which `contains_child`, `is_descendant`
+    /// and `common_descendants` require.
+    Unsorted,
+    /// A `parents` chain never reaches a root, i.e. an item is its own ancestor.
+    Cyclic,
+}
+
+impl std::fmt::Display for ConsolidatedMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsolidatedMapError::Truncated => write!(f, "byte slice is truncated"),
+            ConsolidatedMapError::InvalidMagic => write!(f, "invalid magic bytes"),
+            ConsolidatedMapError::UnsupportedVersion(v) => {
+                write!(f, "unsupported format version {}", v)
+            }
+            ConsolidatedMapError::OutOfBounds => {
+                write!(f, "index or parents entry is out of bounds")
+            }
+            ConsolidatedMapError::Unsorted => {
+                write!(f, "children slice is not sorted")
+            }
+            ConsolidatedMapError::Cyclic => {
+                write!(f, "parents chain never reaches a root")
+            }
+        }
+    }
 }
 
+impl std::error::Error for ConsolidatedMapError {}
+
 impl<T> Default for ConsolidatedMap<T> {
     fn default() -> Self {
         ConsolidatedMap {
             _t: PhantomData,
             data: Vec::new(),
             index: Vec::new(),
+            parents: Vec::new(),
+            child_offsets: vec![0],
+            child_ids: Vec::new(),
         }
     }
 }
@@ -188,20 +746,162 @@ where
     }
 }
 
+/// An iterator over the descendants shared by two keys, returned by
+/// `ConsolidatedMap::common_descendants`.
 #[derive(Clone)]
-struct Entry {
-    children: Vec<u32>,
-    parent: Option<u32>,
+pub struct CommonDescendants<'a, T>(
+    ::std::iter::Peekable<::std::slice::Iter<'a, u32>>,
+    ::std::iter::Peekable<::std::slice::Iter<'a, u32>>,
+    PhantomData<T>,
+);
+
+impl<'a, T> Iterator for CommonDescendants<'a, T>
+where
+    T: From<usize>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (a, b) = (*self.0.peek()?, *self.1.peek()?);
+
+            match a.cmp(b) {
+                std::cmp::Ordering::Less => {
+                    self.0.next();
+                }
+                std::cmp::Ordering::Greater => {
+                    self.1.next();
+                }
+                std::cmp::Ordering::Equal => {
+                    self.0.next();
+                    self.1.next();
+                    return Some((*a as usize).into());
+                }
+            }
+        }
+    }
 }
 
-/// A builder pattern for the ConsolidatedMap.
-pub struct ConsolidatedMapBuilder<T> {
-    _t: PhantomData<T>,
-    entries: Vec<Entry>,
-    len: usize,
+/// An iterator over the ancestors of an item, from its immediate parent up
+/// to the root, returned by `ConsolidatedMap::ancestors`.
+#[derive(Clone)]
+pub struct Ancestors<'a, T> {
+    map: &'a ConsolidatedMap<T>,
+    current: Option<T>,
 }
 
-impl<T> ConsolidatedMapBuilder<T> {
+impl<'a, T> Iterator for Ancestors<'a, T>
+where
+    T: Copy + From<usize> + Into<usize>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let parent = self.map.parent(self.current.take()?);
+        self.current = parent;
+        parent
+    }
+}
+
+/// An iterator over the items that have no parent, returned by
+/// `ConsolidatedMap::roots`.
+#[derive(Clone)]
+pub struct Roots<'a, T> {
+    parents: ::std::iter::Enumerate<::std::slice::Iter<'a, u32>>,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T> Iterator for Roots<'a, T>
+where
+    T: From<usize>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        for (i, &p) in self.parents.by_ref() {
+            if p == NO_PARENT {
+                return Some(i.into());
+            }
+        }
+
+        None
+    }
+}
+
+/// An iterator over the items that have no children, returned by
+/// `ConsolidatedMap::leaves`.
+#[derive(Clone)]
+pub struct Leaves<'a, T> {
+    index: ::std::iter::Enumerate<::std::slice::Iter<'a, usize>>,
+    data: &'a [u32],
+    _t: PhantomData<T>,
+}
+
+impl<'a, T> Iterator for Leaves<'a, T>
+where
+    T: From<usize>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        for (i, &pos) in self.index.by_ref() {
+            if self.data[pos] == 0 {
+                return Some(i.into());
+            }
+        }
+
+        None
+    }
+}
+
+/// A depth-first, pre-order iterator over every item of the forest,
+/// returned by `ConsolidatedMap::preorder`.
+pub struct Preorder<'a, T> {
+    map: &'a ConsolidatedMap<T>,
+    stack: Vec<u32>,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T> Iterator for Preorder<'a, T>
+where
+    T: From<usize> + Into<usize>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let item = self.stack.pop()?;
+
+        let start = self.map.child_offsets[item as usize];
+        let end = self.map.child_offsets[item as usize + 1];
+
+        self.stack
+            .extend(self.map.child_ids[start..end].iter().rev());
+
+        Some((item as usize).into())
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    children: Vec<u32>,
+    parent: Option<u32>,
+}
+
+/// A builder pattern for the ConsolidatedMap.
+///
+/// Every key from `0` up to the highest key ever passed to `insert` gets an
+/// entry (a call whose `parent` equals its `child` is ignored and allocates
+/// nothing), so for sparse key spaces (e.g. ids with large gaps) the
+/// never-inserted keys below that maximum end up as phantom entries: no
+/// parent and no children, which makes each of them simultaneously a root
+/// and a leaf in the built `ConsolidatedMap`.
+pub struct ConsolidatedMapBuilder<T> {
+    _t: PhantomData<T>,
+    entries: Vec<Entry>,
+    len: usize,
+}
+
+impl<T> ConsolidatedMapBuilder<T> {
     pub fn new() -> Self {
         ConsolidatedMapBuilder {
             _t: PhantomData,
@@ -295,33 +995,368 @@ impl<T> ConsolidatedMapBuilder<T> {
     pub fn build(self) -> ConsolidatedMap<T> {
         let mut data = Vec::with_capacity(self.len);
         let mut index = Vec::with_capacity(self.entries.len());
+        let mut parents = Vec::with_capacity(self.entries.len());
 
         for mut entry in self.entries.into_iter() {
             entry.children.sort_unstable();
             index.push(data.len());
             data.push(entry.children.len() as u32);
             data.extend(entry.children.into_iter());
+            parents.push(entry.parent.unwrap_or(NO_PARENT));
+        }
+
+        let child_offsets = child_offsets_from_parents(&parents);
+        let child_ids = child_ids_from_parents(&parents, &child_offsets);
+
+        ConsolidatedMap {
+            _t: PhantomData,
+            data,
+            index,
+            parents,
+            child_offsets,
+            child_ids,
+        }
+    }
+}
+
+/// Builds the CSR offsets of the immediate-children array from a `parents`
+/// array: `child_offsets[item]..child_offsets[item + 1]` is the range, in
+/// `child_ids`, of the immediate children of `item`.
+fn child_offsets_from_parents(parents: &[u32]) -> Vec<usize> {
+    let mut offsets = vec![0usize; parents.len() + 1];
+
+    for &p in parents {
+        if p != NO_PARENT {
+            offsets[p as usize + 1] += 1;
+        }
+    }
+
+    for i in 1..offsets.len() {
+        offsets[i] += offsets[i - 1];
+    }
+
+    offsets
+}
+
+/// Fills the immediate-children ids in the order described by `child_offsets`.
+fn child_ids_from_parents(parents: &[u32], child_offsets: &[usize]) -> Vec<u32> {
+    let mut cursor = child_offsets.to_vec();
+    let mut ids = vec![0u32; *child_offsets.last().unwrap_or(&0)];
+
+    for (i, &p) in parents.iter().enumerate() {
+        if p != NO_PARENT {
+            let pos = cursor[p as usize];
+            ids[pos] = i as u32;
+            cursor[p as usize] += 1;
+        }
+    }
+
+    ids
+}
+
+/// An error returned by `LiveConsolidatedMap::try_insert` or `::reparent`
+/// instead of panicking.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConsolidationError {
+    /// The child already has a different parent than the one being inserted.
+    MultipleParents,
+    /// Inserting the edge would make an item its own ancestor.
+    CircularReference,
+}
+
+impl std::fmt::Display for ConsolidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsolidationError::MultipleParents => write!(f, "child has already a parent"),
+            ConsolidationError::CircularReference => write!(f, "circular reference"),
+        }
+    }
+}
+
+impl std::error::Error for ConsolidationError {}
+
+#[derive(Clone, Default)]
+struct LiveEntry {
+    parent: Option<u32>,
+    /// All descendants of this item (not just immediate children), mirroring
+    /// the consolidated descendant set `ConsolidatedMap` stores per key.
+    descendants: Vec<u32>,
+}
+
+/// A mutable, incrementally-consolidated map built from a reference-counted
+/// bag of `(parent, child)` edges.
+///
+/// Unlike `ConsolidatedMapBuilder`, which ignores a repeated edge, inserting
+/// the same edge more than once bumps its reference count, like the assertion
+/// bags used for incremental Datalog-style fact tracking. `remove` only
+/// detaches an edge on its last reference, and `freeze` collapses the live
+/// structure back into a compact, read-only `ConsolidatedMap`.
+///
+/// # Example
+///
+/// ```
+/// use consolidated_map::LiveConsolidatedMap;
+///
+/// let mut live = LiveConsolidatedMap::new();
+///
+/// live.insert(1usize, 2);
+/// live.insert(1, 2); // same edge again: bumps the reference count
+/// live.insert(2, 3);
+///
+/// let map = live.freeze();
+/// assert_eq!(map.consolidated(1).collect::<Vec<_>>(), vec![1, 2, 3]);
+///
+/// live.remove(1, 2); // one reference left, 2 is still a child of 1
+/// assert_eq!(live.freeze().consolidated(1).collect::<Vec<_>>(), vec![1, 2, 3]);
+///
+/// live.remove(1, 2); // last reference: 2 (and its descendant 3) are detached
+/// assert_eq!(live.freeze().consolidated(1).collect::<Vec<_>>(), vec![1]);
+/// ```
+pub struct LiveConsolidatedMap<T> {
+    _t: PhantomData<T>,
+    entries: Vec<LiveEntry>,
+    edges: std::collections::HashMap<(u32, u32), u32>,
+}
+
+impl<T> Default for LiveConsolidatedMap<T> {
+    fn default() -> Self {
+        LiveConsolidatedMap {
+            _t: PhantomData,
+            entries: Vec::new(),
+            edges: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl<T> LiveConsolidatedMap<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn ensure(&mut self, v: usize) {
+        while self.entries.len() <= v {
+            self.entries.push(LiveEntry::default());
+        }
+    }
+
+    /// Inserts the edge `parent -> child`, bumping its reference count if it
+    /// is already present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `child` already has a different parent, or if the edge
+    /// would make an item its own ancestor. Use `try_insert` to handle these
+    /// cases instead.
+    pub fn insert(&mut self, parent: T, child: T)
+    where
+        T: Copy + Display + Into<usize>,
+    {
+        match self.try_insert(parent, child) {
+            Ok(()) => {}
+            Err(ConsolidationError::MultipleParents) => {
+                panic!("Child {} has already a parent.", child)
+            }
+            Err(ConsolidationError::CircularReference) => panic!(
+                "Circular reference parent: {}, child: {} failed.",
+                parent, child
+            ),
+        }
+    }
+
+    /// Inserts the edge `parent -> child`, bumping its reference count if it
+    /// is already present.
+    ///
+    /// Returns `Err(ConsolidationError::MultipleParents)` if `child` already
+    /// has a different parent, or `Err(ConsolidationError::CircularReference)`
+    /// if the edge would make an item its own ancestor. A `parent` equal to
+    /// `child` is a no-op, like `ConsolidatedMapBuilder::insert`.
+    pub fn try_insert(&mut self, parent: T, child: T) -> Result<(), ConsolidationError>
+    where
+        T: Into<usize>,
+    {
+        let parent_idx = parent.into() as u32;
+        let child_idx = child.into() as u32;
+
+        if parent_idx == child_idx {
+            return Ok(());
+        }
+
+        self.ensure(max(parent_idx, child_idx) as usize);
+
+        let key = (parent_idx, child_idx);
+        if let Some(count) = self.edges.get_mut(&key) {
+            *count += 1;
+            return Ok(());
+        }
+
+        if let Some(existing) = self.entries[child_idx as usize].parent {
+            // Reaching here with `existing == parent_idx` would mean this
+            // exact edge is tracked without a matching `edges` entry, which
+            // would be a bug in `try_insert`/`remove` bookkeeping.
+            debug_assert_ne!(existing, parent_idx);
+            return Err(ConsolidationError::MultipleParents);
+        }
+
+        if self.entries[child_idx as usize]
+            .descendants
+            .contains(&parent_idx)
+        {
+            return Err(ConsolidationError::CircularReference);
+        }
+
+        self.entries[child_idx as usize].parent = Some(parent_idx);
+
+        let mut subtree = self.entries[child_idx as usize].descendants.clone();
+        subtree.push(child_idx);
+
+        let mut ancestor = Some(parent_idx);
+        while let Some(a) = ancestor {
+            let entry = &mut self.entries[a as usize];
+            entry.descendants.extend(subtree.iter().copied());
+            ancestor = entry.parent;
+        }
+
+        self.edges.insert(key, 1);
+
+        Ok(())
+    }
+
+    /// Removes one reference to the edge `parent -> child`.
+    ///
+    /// If this was the last reference, `child` (and its descendants) are
+    /// removed from the descendant list of `parent` and all of its
+    /// ancestors, without a full rebuild. A missing edge is a no-op.
+    pub fn remove(&mut self, parent: T, child: T)
+    where
+        T: Into<usize>,
+    {
+        let parent_idx = parent.into() as u32;
+        let child_idx = child.into() as u32;
+
+        if child_idx as usize >= self.entries.len() {
+            return;
+        }
+
+        let key = (parent_idx, child_idx);
+
+        match self.edges.get_mut(&key) {
+            Some(count) if *count > 1 => *count -= 1,
+            Some(_) => {
+                self.edges.remove(&key);
+                self.detach(parent_idx, child_idx);
+            }
+            None => {}
+        }
+    }
+
+    /// Moves `child` under `new_parent`, detaching it (and dropping all of
+    /// its edge's references) from its current parent first.
+    ///
+    /// Returns `Err(ConsolidationError::CircularReference)` if `new_parent`
+    /// is currently a descendant of `child`.
+    pub fn reparent(&mut self, new_parent: T, child: T) -> Result<(), ConsolidationError>
+    where
+        T: Copy + Into<usize>,
+    {
+        let child_idx = child.into() as u32;
+
+        self.ensure(child_idx as usize);
+
+        if let Some(old_parent) = self.entries[child_idx as usize].parent {
+            self.edges.remove(&(old_parent, child_idx));
+            self.detach(old_parent, child_idx);
         }
 
+        self.try_insert(new_parent, child)
+    }
+
+    /// Detaches `child` from `parent`, subtracting `child`'s subtree from
+    /// `parent` and all of its ancestors. No-op if `child`'s current parent
+    /// is not `parent` (e.g. it was already detached).
+    fn detach(&mut self, parent_idx: u32, child_idx: u32) {
+        if self.entries[child_idx as usize].parent != Some(parent_idx) {
+            return;
+        }
+
+        let mut subtree: std::collections::HashSet<u32> = self.entries[child_idx as usize]
+            .descendants
+            .iter()
+            .copied()
+            .collect();
+        subtree.insert(child_idx);
+
+        self.entries[child_idx as usize].parent = None;
+
+        let mut ancestor = Some(parent_idx);
+        while let Some(a) = ancestor {
+            let entry = &mut self.entries[a as usize];
+            entry.descendants.retain(|d| !subtree.contains(d));
+            ancestor = entry.parent;
+        }
+    }
+
+    /// Collapses the live structure into a compact, read-only `ConsolidatedMap`.
+    pub fn freeze(&self) -> ConsolidatedMap<T> {
+        let mut data = Vec::new();
+        let mut index = Vec::with_capacity(self.entries.len());
+        let mut parents = Vec::with_capacity(self.entries.len());
+
+        for entry in &self.entries {
+            let mut descendants = entry.descendants.clone();
+            descendants.sort_unstable();
+
+            index.push(data.len());
+            data.push(descendants.len() as u32);
+            data.extend(descendants);
+            parents.push(entry.parent.unwrap_or(NO_PARENT));
+        }
+
+        let child_offsets = child_offsets_from_parents(&parents);
+        let child_ids = child_ids_from_parents(&parents, &child_offsets);
+
         ConsolidatedMap {
             _t: PhantomData,
             data,
             index,
+            parents,
+            child_offsets,
+            child_ids,
         }
     }
 }
 
+/// A value that can be folded over a subtree in `ConsolidatedMap::rollup`.
+///
+/// Implementors must form a monoid: `combine` is associative, and
+/// `a.combine(&V::empty())` must equal `a`.
+pub trait Monoid {
+    /// The identity value of the monoid.
+    fn empty() -> Self;
+
+    /// Combines `self` with `other`.
+    fn combine(&self, other: &Self) -> Self;
+}
+
 /// Returns an Iterator that gives all the children and the key
 /// itself associated with a key.
 pub trait ConsolidatedBy<K> {
-    fn consolidated_by(&self, key: K) -> Children<K>;
+    type Iter<'a>: Iterator<Item = K>
+    where
+        Self: 'a;
+
+    fn consolidated_by(&self, key: K) -> Self::Iter<'_>;
 }
 
 impl<K, T> ConsolidatedBy<K> for &T
 where
     T: ConsolidatedBy<K>,
 {
-    fn consolidated_by(&self, key: K) -> Children<K> {
+    type Iter<'a>
+        = T::Iter<'a>
+    where
+        Self: 'a;
+
+    fn consolidated_by(&self, key: K) -> Self::Iter<'_> {
         (*self).consolidated_by(key)
     }
 }
@@ -330,7 +1365,258 @@ impl<K> ConsolidatedBy<K> for ConsolidatedMap<K>
 where
     K: Copy + From<usize> + Into<usize>,
 {
-    fn consolidated_by(&self, key: K) -> Children<K> {
-        (*self).consolidated(key)
+    type Iter<'a>
+        = Children<'a, K>
+    where
+        K: 'a;
+
+    fn consolidated_by(&self, key: K) -> Children<'_, K> {
+        self.consolidated(key)
+    }
+}
+
+/// A read-only, zero-copy view over a map serialized by `ConsolidatedMap::to_bytes`.
+///
+/// `ConsolidatedMapView` borrows the raw bytes (e.g. from an `mmap`) and reads
+/// `children`/`consolidated`/`contains_child` directly out of them, so a large
+/// precomputed map can be opened instantly and shared read-only across
+/// processes without materializing owned `Vec`s.
+pub struct ConsolidatedMapView<'a, T> {
+    _t: PhantomData<T>,
+    bytes: &'a [u8],
+    data_len: usize,
+    data_offset: usize,
+    index_len: usize,
+    index_offset: usize,
+    parents_offset: usize,
+}
+
+impl<'a, T> ConsolidatedMapView<'a, T> {
+    /// Opens a view over bytes previously written by `ConsolidatedMap::to_bytes`.
+    ///
+    /// The header, every `index[p] + 1 + data[index[p]]` bound, the
+    /// sortedness of each resulting children slice, and the acyclicity of
+    /// `parents` are all validated up front, so a successfully opened view
+    /// never reads out of bounds, and never returns wrong answers from
+    /// `contains_child`, afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use consolidated_map::{ConsolidatedMap, ConsolidatedMapView};
+    ///
+    /// let map: ConsolidatedMap<usize> = vec![(1, 2), (2, 3)].into_iter().collect();
+    /// let bytes = map.to_bytes();
+    /// let view = ConsolidatedMapView::<usize>::from_bytes(&bytes).unwrap();
+    ///
+    /// assert_eq!(view.consolidated(1).collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// assert!(view.contains_child(1, 3));
+    /// assert_eq!(view.parent(2), Some(1));
+    /// ```
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, ConsolidatedMapError> {
+        let header = Header::parse(bytes)?;
+
+        for i in 0..header.index_len {
+            let o = header.index_offset + i * 8;
+            let p = u64::from_le_bytes(bytes[o..o + 8].try_into().unwrap()) as usize;
+
+            if p >= header.data_len {
+                return Err(ConsolidatedMapError::OutOfBounds);
+            }
+
+            let lo = header.data_offset + p * 4;
+            let len = u32::from_le_bytes(bytes[lo..lo + 4].try_into().unwrap()) as usize;
+
+            if p.checked_add(1 + len).is_none_or(|end| end > header.data_len) {
+                return Err(ConsolidatedMapError::OutOfBounds);
+            }
+
+            let mut prev = None;
+            for j in 0..len {
+                let eo = header.data_offset + (p + 1 + j) * 4;
+                let v = u32::from_le_bytes(bytes[eo..eo + 4].try_into().unwrap());
+
+                if prev.is_some_and(|prev| prev > v) {
+                    return Err(ConsolidatedMapError::Unsorted);
+                }
+
+                prev = Some(v);
+            }
+        }
+
+        let mut parents = Vec::with_capacity(header.index_len);
+        for i in 0..header.index_len {
+            let o = header.parents_offset + i * 4;
+            parents.push(u32::from_le_bytes(bytes[o..o + 4].try_into().unwrap()));
+        }
+
+        validate_parents(&parents)?;
+
+        Ok(ConsolidatedMapView {
+            _t: PhantomData,
+            bytes,
+            data_len: header.data_len,
+            data_offset: header.data_offset,
+            index_len: header.index_len,
+            index_offset: header.index_offset,
+            parents_offset: header.parents_offset,
+        })
+    }
+
+    fn read_data_u32(&self, i: usize) -> u32 {
+        let o = self.data_offset + i * 4;
+        u32::from_le_bytes(self.bytes[o..o + 4].try_into().unwrap())
+    }
+
+    fn read_index_u64(&self, i: usize) -> u64 {
+        let o = self.index_offset + i * 8;
+        u64::from_le_bytes(self.bytes[o..o + 8].try_into().unwrap())
+    }
+
+    fn read_parent_u32(&self, i: usize) -> u32 {
+        let o = self.parents_offset + i * 4;
+        u32::from_le_bytes(self.bytes[o..o + 4].try_into().unwrap())
+    }
+
+    /// Returns the parent of `item`, or `None` if it is a root or was never inserted.
+    pub fn parent(&self, item: T) -> Option<T>
+    where
+        T: From<usize> + Into<usize>,
+    {
+        let item = item.into();
+        if item >= self.index_len {
+            return None;
+        }
+
+        let parent = self.read_parent_u32(item);
+        if parent == NO_PARENT {
+            None
+        } else {
+            Some((parent as usize).into())
+        }
+    }
+
+    /// Returns the start and length, in `data` slots, of the children of `parent`.
+    fn get_children_range(&self, parent: T) -> Option<(usize, usize)>
+    where
+        T: Into<usize>,
+    {
+        let parent = parent.into();
+        if parent >= self.index_len {
+            return None;
+        }
+
+        let index = self.read_index_u64(parent) as usize;
+        if index >= self.data_len {
+            return None;
+        }
+
+        let len = self.read_data_u32(index) as usize;
+        Some((index + 1, len))
+    }
+
+    /// Returns an iterator containing all the children of an item.
+    pub fn children(&self, item: T) -> ViewChildren<'a, T>
+    where
+        T: From<usize> + Into<usize>,
+    {
+        let (start, len) = self.get_children_range(item).unwrap_or((0, 0));
+
+        ViewChildren {
+            bytes: self.bytes,
+            offset: self.data_offset,
+            pos: start,
+            end: start + len,
+            prefix: None,
+        }
+    }
+
+    /// Returns an iterator containing all the children of an item with the specified item.
+    pub fn consolidated(&self, item: T) -> ViewChildren<'a, T>
+    where
+        T: Copy + From<usize> + Into<usize>,
+    {
+        let (start, len) = self.get_children_range(item).unwrap_or((0, 0));
+
+        ViewChildren {
+            bytes: self.bytes,
+            offset: self.data_offset,
+            pos: start,
+            end: start + len,
+            prefix: Some(item),
+        }
+    }
+
+    /// Returns true if a parent contains the child, using a binary search
+    /// over the children range read directly out of the borrowed bytes.
+    pub fn contains_child(&self, parent: T, child: T) -> bool
+    where
+        T: Into<usize>,
+    {
+        let child = child.into() as u32;
+
+        let (start, len) = match self.get_children_range(parent) {
+            Some(range) => range,
+            None => return false,
+        };
+
+        let (mut lo, mut hi) = (0usize, len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.read_data_u32(start + mid).cmp(&child) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return true,
+            }
+        }
+
+        false
+    }
+}
+
+impl<'b, K> ConsolidatedBy<K> for ConsolidatedMapView<'b, K>
+where
+    K: Copy + From<usize> + Into<usize>,
+{
+    type Iter<'a>
+        = ViewChildren<'b, K>
+    where
+        Self: 'a;
+
+    fn consolidated_by(&self, key: K) -> ViewChildren<'b, K> {
+        self.consolidated(key)
+    }
+}
+
+/// An iterator containing all the children of an item, read directly out of
+/// the bytes borrowed by a `ConsolidatedMapView`.
+pub struct ViewChildren<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    pos: usize,
+    end: usize,
+    prefix: Option<T>,
+}
+
+impl<'a, T> Iterator for ViewChildren<'a, T>
+where
+    T: From<usize>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.prefix.take() {
+            return Some(item);
+        }
+
+        if self.pos >= self.end {
+            return None;
+        }
+
+        let o = self.offset + self.pos * 4;
+        let u = u32::from_le_bytes(self.bytes[o..o + 4].try_into().unwrap());
+        self.pos += 1;
+
+        Some((u as usize).into())
     }
 }